@@ -0,0 +1,4 @@
+//! Client implementations for the various model/embedding providers `rig` supports.
+
+pub mod openai;
+pub mod rest;
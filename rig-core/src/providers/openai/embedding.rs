@@ -1,12 +1,92 @@
 use super::{ApiErrorResponse, ApiResponse, Client, Usage};
 use crate::embeddings;
 use crate::embeddings::EmbeddingError;
-use reqwest::{header::HeaderValue, StatusCode};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    StatusCode,
+};
 use serde::Deserialize;
 use serde_json::json;
+use std::sync::OnceLock;
 use std::time::Duration;
+use tiktoken_rs::CoreBPE;
 use tokio::time::sleep; // Already present, no change needed here, but confirming it's used.
 
+/// Maximum number of retry attempts for a single batch before giving up.
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// Default number of `/embeddings` requests to have in flight at once.
+const DEFAULT_REQUEST_PARALLELISM: usize = 1;
+
+/// Max tokens per document accepted by the `text-embedding-3-*`/`text-embedding-ada-002` models.
+const MAX_TOKENS_PER_DOCUMENT: usize = 8191;
+
+/// Max total tokens OpenAI accepts across all documents in a single `/embeddings` request.
+const MAX_TOKENS_PER_REQUEST: usize = 300_000;
+
+/// Returns the `cl100k_base` tokenizer used by the `text-embedding-3-*`/`text-embedding-ada-002`
+/// models, constructing it on first use and sharing it across every call after that.
+///
+/// `tiktoken-rs` loads its BPE rank data lazily (and, depending on environment, over the
+/// network) the first time this runs, so construction can fail; callers get that back as an
+/// [`EmbeddingError`] rather than a panic.
+fn tokenizer() -> Result<&'static CoreBPE, EmbeddingError> {
+    static TOKENIZER: OnceLock<Result<CoreBPE, String>> = OnceLock::new();
+    TOKENIZER
+        .get_or_init(|| tiktoken_rs::cl100k_base().map_err(|err| err.to_string()))
+        .as_ref()
+        .map_err(|err| {
+            EmbeddingError::ProviderError(format!("Failed to load cl100k_base tokenizer: {err}"))
+        })
+}
+
+/// Counts how many `cl100k_base` tokens `text` encodes to.
+fn count_tokens(text: &str) -> Result<usize, EmbeddingError> {
+    Ok(tokenizer()?.encode_with_special_tokens(text).len())
+}
+
+/// Packs `documents` into request-sized batches, respecting both `max_documents` and a total
+/// `max_tokens_per_request` budget, and rejects any single document over `max_tokens_per_document`
+/// up front rather than letting the provider reject it after a round-trip.
+fn pack_into_token_budget(
+    documents: Vec<String>,
+    max_documents: usize,
+    max_tokens_per_document: usize,
+    max_tokens_per_request: usize,
+) -> Result<Vec<Vec<String>>, EmbeddingError> {
+    let mut chunks = Vec::new();
+    let mut current_chunk = Vec::new();
+    let mut current_chunk_tokens = 0usize;
+
+    for document in documents {
+        let tokens = count_tokens(&document)?;
+        if tokens > max_tokens_per_document {
+            return Err(EmbeddingError::DocumentError(format!(
+                "Document has {} tokens, which exceeds the model's {}-token limit",
+                tokens, max_tokens_per_document
+            )));
+        }
+
+        let chunk_is_full = current_chunk.len() >= max_documents
+            || current_chunk_tokens + tokens > max_tokens_per_request;
+
+        if !current_chunk.is_empty() && chunk_is_full {
+            chunks.push(std::mem::take(&mut current_chunk));
+            current_chunk_tokens = 0;
+        }
+
+        current_chunk_tokens += tokens;
+        current_chunk.push(document);
+    }
+
+    if !current_chunk.is_empty() {
+        chunks.push(current_chunk);
+    }
+
+    Ok(chunks)
+}
+
 // ================================================================
 // OpenAI Embedding API
 // ================================================================
@@ -52,6 +132,42 @@ pub struct EmbeddingModel {
     client: Client,
     pub model: String,
     ndims: usize,
+    dimensions: Option<usize>,
+    request_parallelism: usize,
+    distribution_shift: Option<DistributionShift>,
+}
+
+/// Calibrates raw cosine-similarity scores from an embedding model into a comparable 0..1 range,
+/// via a shifted sigmoid: `1 / (1 + exp(-(raw - mean) / sigma))`, clamped to `[0, 1]`.
+///
+/// Different embedding models spread cosine similarities over different ranges, which makes raw
+/// scores hard to threshold or compare across models. `mean` and `sigma` should be picked (or
+/// measured) so that a typical "relevant" raw score normalizes to roughly `0.5` or higher.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistributionShift {
+    pub mean: f64,
+    pub sigma: f64,
+}
+
+impl DistributionShift {
+    /// Default calibration for [`TEXT_EMBEDDING_3_SMALL`].
+    pub const TEXT_EMBEDDING_3_SMALL: Self = Self {
+        mean: 0.42,
+        sigma: 0.05,
+    };
+
+    /// Default calibration for [`TEXT_EMBEDDING_3_LARGE`]. `text-embedding-3-large` spreads
+    /// cosine similarities lower and wider than `-3-small`, so it gets its own mean/sigma.
+    pub const TEXT_EMBEDDING_3_LARGE: Self = Self {
+        mean: 0.24,
+        sigma: 0.08,
+    };
+
+    /// Normalizes a raw cosine-similarity score to `[0, 1]`.
+    pub fn normalize(&self, raw_score: f64) -> f64 {
+        let normalized = 1.0 / (1.0 + (-(raw_score - self.mean) / self.sigma).exp());
+        normalized.clamp(0.0, 1.0)
+    }
 }
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
@@ -67,95 +183,303 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents_vec = documents.into_iter().collect::<Vec<_>>();
-        let request_body = json!({
-            "model": self.model,
-            "input": documents_vec,
-        });
 
-        loop {
-            let response = self
-                .client
-                .post("/embeddings")
-                .json(&request_body)
-                .send()
-                .await?;
-
-            match response.status() {
-                StatusCode::OK => {
-                    // Success case
-                    match response.json::<ApiResponse<EmbeddingResponse>>().await? {
-                        ApiResponse::Ok(response_data) => {
-                            tracing::info!(target: "rig",
-                                "OpenAI embedding token usage: {}",
-                                response_data.usage
-                            );
-
-                            if response_data.data.len() != documents_vec.len() {
-                                return Err(EmbeddingError::ResponseError(
-                                    "Response data length does not match input length".into(),
-                                ));
-                            }
+        let chunks = pack_into_token_budget(
+            documents_vec,
+            Self::MAX_DOCUMENTS,
+            MAX_TOKENS_PER_DOCUMENT,
+            MAX_TOKENS_PER_REQUEST,
+        )?;
 
-                            return Ok(response_data
-                                .data
-                                .into_iter()
-                                .zip(documents_vec.into_iter()) // Use the original vec here
-                                .map(|(embedding, document)| embeddings::Embedding {
-                                    document,
-                                    vec: embedding.embedding,
-                                })
-                                .collect());
-                        }
-                        ApiResponse::Err(err) => {
-                            return Err(EmbeddingError::ProviderError(err.message))
-                        }
+        let chunk_results: Vec<Vec<embeddings::Embedding>> = stream::iter(chunks)
+            .map(|chunk| self.embed_chunk(chunk))
+            .buffered(self.request_parallelism.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(chunk_results.into_iter().flatten().collect())
+    }
+}
+
+impl EmbeddingModel {
+    /// Returns a lower bound on how many `/embeddings` requests [`EmbeddingModel::embed_texts`]
+    /// would issue for `document_count` documents, based on [`Self::MAX_DOCUMENTS`] alone (the
+    /// actual count may be higher once the token budget is taken into account). Useful for sizing
+    /// `request_parallelism` when indexing a large corpus.
+    pub fn chunk_count_hint(&self, document_count: usize) -> usize {
+        document_count.div_ceil(<Self as embeddings::EmbeddingModel>::MAX_DOCUMENTS)
+    }
+
+    /// Sets how many chunked `/embeddings` requests may be in flight at once. Defaults to
+    /// [`DEFAULT_REQUEST_PARALLELISM`].
+    pub fn with_request_parallelism(mut self, request_parallelism: usize) -> Self {
+        self.request_parallelism = request_parallelism;
+        self
+    }
+
+    /// Sets the [`DistributionShift`] used by [`EmbeddingModel::normalize_similarity`] to
+    /// calibrate raw cosine-similarity scores for this model.
+    pub fn with_distribution_shift(mut self, distribution_shift: DistributionShift) -> Self {
+        self.distribution_shift = Some(distribution_shift);
+        self
+    }
+
+    /// Normalizes a raw cosine-similarity score using this model's configured
+    /// [`DistributionShift`], if any; otherwise returns `raw_score` unchanged.
+    pub fn normalize_similarity(&self, raw_score: f64) -> f64 {
+        match &self.distribution_shift {
+            Some(distribution_shift) => distribution_shift.normalize(raw_score),
+            None => raw_score,
+        }
+    }
+
+    /// Computes the cosine similarity between two embeddings produced by this model, normalized
+    /// via [`EmbeddingModel::normalize_similarity`] so the result is comparable across models
+    /// with different [`DistributionShift`] calibrations.
+    pub fn similarity(&self, a: &embeddings::Embedding, b: &embeddings::Embedding) -> f64 {
+        self.normalize_similarity(cosine_similarity(&a.vec, &b.vec))
+    }
+
+    /// Embeds a chunk of at most [`Self::MAX_DOCUMENTS`] documents, retrying and splitting the
+    /// chunk as needed. This is the unit of work dispatched concurrently by
+    /// [`EmbeddingModel::embed_texts`].
+    async fn embed_chunk(
+        &self,
+        documents_vec: Vec<String>,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        let mut results: Vec<Option<embeddings::Embedding>> =
+            (0..documents_vec.len()).map(|_| None).collect();
+        // Batches still needing a (re)attempt, as (offset into `results`, batch, attempt count).
+        let mut pending = vec![(0usize, documents_vec, 0u32)];
+
+        while let Some((offset, batch, attempt)) = pending.pop() {
+            match self.try_embed_batch(&batch).await {
+                Ok(batch_embeddings) => {
+                    for (i, embedding) in batch_embeddings.into_iter().enumerate() {
+                        results[offset + i] = Some(embedding);
                     }
                 }
-                StatusCode::TOO_MANY_REQUESTS => {
-                    // Rate limit exceeded, extract retry duration and wait
-                    let retry_after = response
-                        .headers()
-                        .get("x-ratelimit-reset-requests")
-                        .or_else(|| response.headers().get("x-ratelimit-reset-tokens"))
-                        .and_then(parse_ratelimit_duration);
-
-                    if let Some(duration) = retry_after {
-                        tracing::warn!(target: "rig",
-                            "Rate limit hit for OpenAI embeddings. Retrying after {:?}",
-                            duration
-                        );
-                        sleep(duration).await;
-                        continue; // Retry the request
-                    } else {
-                        // Header not found or couldn't parse, return error
-                        let error_text = response.text().await?;
-                        tracing::error!(target: "rig",
-                            "Rate limit hit for OpenAI embeddings, but couldn't parse retry duration. Response: {}",
-                            error_text
-                        );
-                        return Err(EmbeddingError::ProviderError(format!(
-                            "Rate limit hit, but no valid retry duration found in headers. Response: {}",
-                            error_text
-                        )));
+                Err(retry) => match plan_retry(offset, batch, attempt, retry) {
+                    RetryAction::Split(entries) => pending.extend(entries),
+                    RetryAction::Wait { wait, next } => {
+                        sleep(wait).await;
+                        pending.push(next);
                     }
-                }
-                status => {
-                    // Other error status codes
-                    let error_text = response.text().await?;
-                    tracing::error!(target: "rig",
-                        "OpenAI embedding request failed with status {}: {}",
-                        status, error_text
-                    );
-                    return Err(EmbeddingError::ProviderError(format!(
-                        "Request failed with status {}: {}",
-                        status, error_text
-                    )));
-                }
+                    RetryAction::GiveUp(error) => return Err(error),
+                },
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|embedding| embedding.expect("every document is embedded exactly once"))
+            .collect())
+    }
+
+    /// Embeds a single batch of documents, without retrying. Returns a [`Retry`] describing how
+    /// (or whether) the caller should retry on failure.
+    async fn try_embed_batch(
+        &self,
+        documents: &[String],
+    ) -> Result<Vec<embeddings::Embedding>, Retry> {
+        let mut request_body = json!({
+            "model": self.model,
+            "input": documents,
+        });
+        if let Some(dimensions) = self.dimensions {
+            request_body["dimensions"] = json!(dimensions);
+        }
+
+        let response = self
+            .client
+            .post("/embeddings")
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|err| Retry::give_up(EmbeddingError::from(err)))?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+
+        if status != StatusCode::OK {
+            let error_text = response
+                .text()
+                .await
+                .map_err(|err| Retry::give_up(EmbeddingError::from(err)))?;
+            return Err(Retry::classify(status, &headers, error_text));
+        }
+
+        let response_data = match response
+            .json::<ApiResponse<EmbeddingResponse>>()
+            .await
+            .map_err(|err| Retry::give_up(EmbeddingError::from(err)))?
+        {
+            ApiResponse::Ok(response_data) => response_data,
+            ApiResponse::Err(err) => {
+                return Err(Retry::give_up(EmbeddingError::ProviderError(err.message)))
+            }
+        };
+
+        tracing::info!(target: "rig",
+            "OpenAI embedding token usage: {}",
+            response_data.usage
+        );
+
+        if response_data.data.len() != documents.len() {
+            return Err(Retry::give_up(EmbeddingError::ResponseError(
+                "Response data length does not match input length".into(),
+            )));
+        }
+
+        Ok(response_data
+            .data
+            .into_iter()
+            .zip(documents.iter().cloned())
+            .map(|(embedding, document)| embeddings::Embedding {
+                document,
+                vec: embedding.embedding,
+            })
+            .collect())
+    }
+}
+
+/// What to do after a failed embedding request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RetryStrategy {
+    /// The error is not retryable; propagate it to the caller.
+    GiveUp,
+    /// A transient (e.g. server-side) error; wait and retry the same batch.
+    Retry,
+    /// The provider is rate-limiting us; wait (preferably for as long as it tells us) and retry.
+    RetryAfterRateLimit,
+    /// The batch was rejected for having too many tokens; split it and retry the halves.
+    RetryTokenized,
+}
+
+/// What [`EmbeddingModel::embed_chunk`] should do next after a batch attempt fails.
+enum RetryAction {
+    /// Split the batch in half and retry each half from attempt 0.
+    Split(Vec<(usize, Vec<String>, u32)>),
+    /// Wait, then retry `next` (the same batch, with its attempt count incremented).
+    Wait {
+        wait: Duration,
+        next: (usize, Vec<String>, u32),
+    },
+    /// Nothing left to retry; propagate the error.
+    GiveUp(EmbeddingError),
+}
+
+/// Pure decision logic for [`EmbeddingModel::embed_chunk`]'s retry loop, split out from the async
+/// function so it can be unit-tested without a live HTTP client.
+fn plan_retry(offset: usize, batch: Vec<String>, attempt: u32, retry: Retry) -> RetryAction {
+    if retry.strategy == RetryStrategy::RetryTokenized && batch.len() > 1 {
+        let mut first_half = batch;
+        let second_half = first_half.split_off(first_half.len() / 2);
+        let second_offset = offset + first_half.len();
+        return RetryAction::Split(vec![(offset, first_half, 0), (second_offset, second_half, 0)]);
+    }
+    // A single document that's still too large to fit can't be split any further, so retrying it
+    // would just burn attempts on a request that can never succeed.
+    if retry.strategy == RetryStrategy::RetryTokenized {
+        return RetryAction::GiveUp(retry.error);
+    }
+    if attempt >= MAX_RETRY_ATTEMPTS {
+        return RetryAction::GiveUp(retry.error);
+    }
+    match retry.into_duration(attempt) {
+        Ok(wait) => RetryAction::Wait {
+            wait,
+            next: (offset, batch, attempt + 1),
+        },
+        Err(error) => RetryAction::GiveUp(error),
+    }
+}
+
+/// Carries the error that caused a retry decision alongside the decision itself.
+struct Retry {
+    error: EmbeddingError,
+    strategy: RetryStrategy,
+    /// Wait duration read off a rate-limit response header, if one was present and parseable.
+    retry_after: Option<Duration>,
+}
+
+impl Retry {
+    fn give_up(error: EmbeddingError) -> Self {
+        Self {
+            error,
+            strategy: RetryStrategy::GiveUp,
+            retry_after: None,
+        }
+    }
+
+    /// Classifies a non-2xx response into a [`Retry`].
+    fn classify(status: StatusCode, headers: &HeaderMap, message: String) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = headers
+                .get("x-ratelimit-reset-requests")
+                .or_else(|| headers.get("x-ratelimit-reset-tokens"))
+                .and_then(parse_ratelimit_duration);
+
+            Self {
+                error: EmbeddingError::ProviderError(message),
+                strategy: RetryStrategy::RetryAfterRateLimit,
+                retry_after,
+            }
+        } else if status.is_server_error() {
+            Self {
+                error: EmbeddingError::ProviderError(message),
+                strategy: RetryStrategy::Retry,
+                retry_after: None,
             }
+        } else if is_too_many_tokens_error(&message) {
+            Self {
+                error: EmbeddingError::ProviderError(message),
+                strategy: RetryStrategy::RetryTokenized,
+                retry_after: None,
+            }
+        } else {
+            Self::give_up(EmbeddingError::ProviderError(format!(
+                "Request failed with status {}: {}",
+                status, message
+            )))
+        }
+    }
+
+    /// Returns how long to wait before the given (zero-indexed) attempt, or propagates `error`
+    /// if the strategy is [`RetryStrategy::GiveUp`].
+    fn into_duration(self, attempt: u32) -> Result<Duration, EmbeddingError> {
+        match self.strategy {
+            RetryStrategy::GiveUp => Err(self.error),
+            RetryStrategy::Retry => Ok(Duration::from_millis(10u64.saturating_pow(attempt))),
+            RetryStrategy::RetryAfterRateLimit => Ok(self
+                .retry_after
+                .unwrap_or_else(|| Duration::from_millis(100 + 10u64.saturating_pow(attempt)))),
+            RetryStrategy::RetryTokenized => Ok(Duration::from_millis(1)),
         }
     }
 }
 
+/// Raw cosine similarity between two vectors, in `[-1, 1]`. Returns `0.0` if either vector has
+/// zero magnitude, since the angle between them is undefined.
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let dot_product = a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+/// Whether a provider error message indicates the request had too many tokens, as opposed to
+/// some other kind of failure.
+fn is_too_many_tokens_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("maximum context length") || message.contains("too many tokens")
+}
+
 /// Parses OpenAI's rate limit duration string (e.g., "6m10s", "500ms", "1s") into a Duration.
 fn parse_ratelimit_duration(header_value: &HeaderValue) -> Option<Duration> {
     header_value.to_str().ok().and_then(|s| {
@@ -239,6 +563,322 @@ impl EmbeddingModel {
             client,
             model: model.to_string(),
             ndims,
+            dimensions: None,
+            request_parallelism: DEFAULT_REQUEST_PARALLELISM,
+            distribution_shift: None,
+        }
+    }
+
+    /// Sets the OpenAI `dimensions` parameter, which asks `text-embedding-3-small`/
+    /// `text-embedding-3-large` to truncate the returned embedding server-side. This also
+    /// updates `ndims()` to `dimensions`, since that's the length callers will actually get back.
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self.ndims = dimensions;
+        self
+    }
+
+    /// Builds an `EmbeddingModel` without requiring the caller to know `model`'s dimensionality
+    /// up front. This issues a single probe request embedding a trivial string and reads back
+    /// `ndims` from the response, so it should not be called on a hot path.
+    pub async fn new_inferred(client: Client, model: &str) -> Result<Self, EmbeddingError> {
+        let request_body = json!({
+            "model": model,
+            "input": ["rig"],
+        });
+
+        let response = client
+            .post("/embeddings")
+            .json(&request_body)
+            .send()
+            .await?;
+
+        let response_data: EmbeddingResponse = match response
+            .json::<ApiResponse<EmbeddingResponse>>()
+            .await?
+        {
+            ApiResponse::Ok(response_data) => response_data,
+            ApiResponse::Err(err) => return Err(EmbeddingError::ProviderError(err.message)),
+        };
+
+        let ndims = response_data
+            .data
+            .first()
+            .ok_or_else(|| {
+                EmbeddingError::ResponseError("Probe response contained no embeddings".into())
+            })?
+            .embedding
+            .len();
+
+        Ok(Self::new(client, model, ndims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(len: usize) -> String {
+        "a".repeat(len)
+    }
+
+    #[test]
+    fn pack_into_token_budget_respects_max_documents() {
+        let documents = vec![doc(1), doc(1), doc(1), doc(1), doc(1)];
+
+        let chunks = pack_into_token_budget(documents, 2, MAX_TOKENS_PER_DOCUMENT, usize::MAX)
+            .expect("small documents should never hit the token limit");
+
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+    }
+
+    #[test]
+    fn pack_into_token_budget_respects_token_budget() {
+        let documents = vec![doc(4), doc(4), doc(4)];
+        let tokens_per_document = count_tokens(&doc(4)).expect("tokenizer should be available");
+
+        let chunks = pack_into_token_budget(
+            documents,
+            usize::MAX,
+            MAX_TOKENS_PER_DOCUMENT,
+            tokens_per_document * 2,
+        )
+        .expect("documents individually fit within the per-document limit");
+
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+    }
+
+    #[test]
+    fn pack_into_token_budget_rejects_oversized_document() {
+        let documents = vec![doc(1000)];
+
+        let result = pack_into_token_budget(documents, usize::MAX, 1, usize::MAX);
+
+        assert!(matches!(result, Err(EmbeddingError::DocumentError(_))));
+    }
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use super::*;
+
+    #[test]
+    fn retry_classifies_rate_limit_as_retry_after_rate_limit() {
+        let retry = Retry::classify(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), "".into());
+
+        assert_eq!(retry.strategy, RetryStrategy::RetryAfterRateLimit);
+    }
+
+    #[test]
+    fn retry_classifies_server_error_as_retry() {
+        let retry = Retry::classify(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &HeaderMap::new(),
+            "".into(),
+        );
+
+        assert_eq!(retry.strategy, RetryStrategy::Retry);
+    }
+
+    #[test]
+    fn retry_classifies_too_many_tokens_message_as_retry_tokenized() {
+        let retry = Retry::classify(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            "This model's maximum context length is 8191 tokens".into(),
+        );
+
+        assert_eq!(retry.strategy, RetryStrategy::RetryTokenized);
+    }
+
+    #[test]
+    fn retry_classifies_other_client_errors_as_give_up() {
+        let retry = Retry::classify(StatusCode::UNAUTHORIZED, &HeaderMap::new(), "nope".into());
+
+        assert_eq!(retry.strategy, RetryStrategy::GiveUp);
+    }
+
+    #[test]
+    fn retry_into_duration_give_up_propagates_error() {
+        let retry = Retry::give_up(EmbeddingError::ProviderError("boom".into()));
+
+        assert!(retry.into_duration(0).is_err());
+    }
+
+    #[test]
+    fn retry_into_duration_retry_backs_off_exponentially() {
+        let retry = Retry::classify(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &HeaderMap::new(),
+            "".into(),
+        );
+
+        assert_eq!(retry.into_duration(3).unwrap(), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn retry_into_duration_tokenized_is_immediate() {
+        let retry = Retry::classify(
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            "too many tokens in request".into(),
+        );
+
+        assert_eq!(retry.into_duration(0).unwrap(), Duration::from_millis(1));
+    }
+}
+
+#[cfg(test)]
+mod distribution_shift_tests {
+    use super::*;
+
+    #[test]
+    fn default_calibrations_differ_per_model() {
+        assert_ne!(
+            DistributionShift::TEXT_EMBEDDING_3_SMALL,
+            DistributionShift::TEXT_EMBEDDING_3_LARGE,
+        );
+    }
+
+    #[test]
+    fn normalize_at_mean_is_one_half() {
+        let distribution_shift = DistributionShift {
+            mean: 0.3,
+            sigma: 0.1,
+        };
+
+        assert_eq!(distribution_shift.normalize(0.3), 0.5);
+    }
+
+    #[test]
+    fn normalize_clamps_to_unit_range() {
+        let distribution_shift = DistributionShift {
+            mean: 0.0,
+            sigma: 0.01,
+        };
+
+        assert_eq!(distribution_shift.normalize(100.0), 1.0);
+        assert_eq!(distribution_shift.normalize(-100.0), 0.0);
+    }
+
+    #[test]
+    fn cosine_similarity_of_identical_vectors_is_one() {
+        let similarity = cosine_similarity(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]);
+
+        assert!((similarity - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cosine_similarity_of_orthogonal_vectors_is_zero() {
+        let similarity = cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]);
+
+        assert!(similarity.abs() < 1e-9);
+    }
+
+    #[test]
+    fn cosine_similarity_of_zero_vector_is_zero() {
+        let similarity = cosine_similarity(&[0.0, 0.0], &[1.0, 2.0]);
+
+        assert_eq!(similarity, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod dimensions_tests {
+    use super::*;
+
+    #[test]
+    fn with_dimensions_updates_ndims() {
+        let client = Client::new("test-api-key");
+        let model = EmbeddingModel::new(client, TEXT_EMBEDDING_3_SMALL, 1536).with_dimensions(256);
+
+        assert_eq!(embeddings::EmbeddingModel::ndims(&model), 256);
+    }
+}
+
+#[cfg(test)]
+mod chunk_count_hint_tests {
+    use super::*;
+
+    fn model() -> EmbeddingModel {
+        EmbeddingModel::new(Client::new("test-api-key"), TEXT_EMBEDDING_3_SMALL, 1536)
+    }
+
+    #[test]
+    fn zero_documents_need_no_requests() {
+        assert_eq!(model().chunk_count_hint(0), 0);
+    }
+
+    #[test]
+    fn exactly_max_documents_fit_in_one_request() {
+        assert_eq!(
+            model().chunk_count_hint(<EmbeddingModel as embeddings::EmbeddingModel>::MAX_DOCUMENTS),
+            1
+        );
+    }
+
+    #[test]
+    fn one_over_max_documents_rounds_up_to_two_requests() {
+        assert_eq!(
+            model().chunk_count_hint(<EmbeddingModel as embeddings::EmbeddingModel>::MAX_DOCUMENTS + 1),
+            2
+        );
+    }
+}
+
+#[cfg(test)]
+mod embed_chunk_retry_tests {
+    use super::*;
+
+    fn batch(n: usize) -> Vec<String> {
+        (0..n).map(|i| i.to_string()).collect()
+    }
+
+    fn retry(strategy: RetryStrategy) -> Retry {
+        Retry {
+            error: EmbeddingError::ProviderError("boom".into()),
+            strategy,
+            retry_after: None,
+        }
+    }
+
+    #[test]
+    fn splits_multi_document_batch_on_retry_tokenized() {
+        let action = plan_retry(10, batch(4), 0, retry(RetryStrategy::RetryTokenized));
+
+        match action {
+            RetryAction::Split(entries) => assert_eq!(
+                entries,
+                vec![
+                    (10, vec!["0".to_string(), "1".to_string()], 0),
+                    (12, vec!["2".to_string(), "3".to_string()], 0),
+                ]
+            ),
+            _ => panic!("expected the batch to be split"),
+        }
+    }
+
+    #[test]
+    fn gives_up_on_single_document_retry_tokenized() {
+        let action = plan_retry(0, batch(1), 0, retry(RetryStrategy::RetryTokenized));
+
+        assert!(matches!(action, RetryAction::GiveUp(_)));
+    }
+
+    #[test]
+    fn gives_up_once_max_retry_attempts_reached() {
+        let action = plan_retry(0, batch(2), MAX_RETRY_ATTEMPTS, retry(RetryStrategy::Retry));
+
+        assert!(matches!(action, RetryAction::GiveUp(_)));
+    }
+
+    #[test]
+    fn waits_and_retries_same_batch_on_transient_error() {
+        let action = plan_retry(0, batch(2), 1, retry(RetryStrategy::Retry));
+
+        match action {
+            RetryAction::Wait { next, .. } => assert_eq!(next, (0, batch(2), 2)),
+            _ => panic!("expected a wait-and-retry"),
         }
     }
 }
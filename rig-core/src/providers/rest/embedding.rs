@@ -0,0 +1,280 @@
+use crate::embeddings::{self, EmbeddingError};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use reqwest::Client as HttpClient;
+use serde_json::Value;
+
+/// Placeholder that [`EmbeddingModel`] looks for inside the request template passed to
+/// [`EmbeddingModel::new`].
+/// Wherever this string appears (as a JSON string value, at any depth), it is replaced with the
+/// JSON array of documents being embedded.
+pub const INPUT_PLACEHOLDER: &str = "{{input}}";
+
+/// Default number of `embed_texts` requests to have in flight at once.
+const DEFAULT_REQUEST_PARALLELISM: usize = 1;
+
+/// An [`embeddings::EmbeddingModel`] that targets an arbitrary HTTP embedding endpoint.
+///
+/// The caller supplies a request-body template (with [`INPUT_PLACEHOLDER`] marking where the
+/// batch of documents should be injected) and a `response_path`, i.e. the sequence of object
+/// keys to walk from the root of the response JSON down to the array of embedding vectors. This
+/// lets `rig` talk to self-hosted or otherwise non-standard embedding APIs without a
+/// hand-written provider module per vendor.
+#[derive(Clone)]
+pub struct EmbeddingModel {
+    http_client: HttpClient,
+    url: String,
+    bearer_token: Option<String>,
+    request_template: Value,
+    response_path: Vec<String>,
+    ndims: usize,
+    request_parallelism: usize,
+}
+
+impl EmbeddingModel {
+    /// Creates a new REST embedding model.
+    ///
+    /// `request_template` must contain [`INPUT_PLACEHOLDER`] somewhere, which is replaced with
+    /// the JSON array of input documents at embed time. `response_path` is the list of object
+    /// keys (applied in order, starting from the response root) that leads to the JSON array of
+    /// embedding vectors.
+    pub fn new(
+        url: impl Into<String>,
+        request_template: Value,
+        response_path: Vec<String>,
+        ndims: usize,
+    ) -> Self {
+        Self {
+            http_client: HttpClient::new(),
+            url: url.into(),
+            bearer_token: None,
+            request_template,
+            response_path,
+            ndims,
+            request_parallelism: DEFAULT_REQUEST_PARALLELISM,
+        }
+    }
+
+    /// Sets a bearer token to send as an `Authorization` header on every request.
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Sets how many chunked embedding requests may be in flight at once. Defaults to
+    /// [`DEFAULT_REQUEST_PARALLELISM`].
+    pub fn with_request_parallelism(mut self, request_parallelism: usize) -> Self {
+        self.request_parallelism = request_parallelism;
+        self
+    }
+
+    /// Renders the request template by substituting [`INPUT_PLACEHOLDER`] with `documents`.
+    fn render_request(&self, documents: &[String]) -> Value {
+        fn render(template: &Value, documents: &[String]) -> Value {
+            match template {
+                Value::String(s) if s == INPUT_PLACEHOLDER => {
+                    Value::Array(documents.iter().cloned().map(Value::String).collect())
+                }
+                Value::Array(items) => {
+                    Value::Array(items.iter().map(|item| render(item, documents)).collect())
+                }
+                Value::Object(map) => Value::Object(
+                    map.iter()
+                        .map(|(key, value)| (key.clone(), render(value, documents)))
+                        .collect(),
+                ),
+                other => other.clone(),
+            }
+        }
+
+        render(&self.request_template, documents)
+    }
+
+    /// Walks `response_path` from the root of `response` and returns the embedding vectors found
+    /// there, failing if the path doesn't resolve to an array of number arrays.
+    fn extract_embeddings(&self, response: Value) -> Result<Vec<Vec<f64>>, EmbeddingError> {
+        let mut current = &response;
+        for key in &self.response_path {
+            current = current.get(key).ok_or_else(|| {
+                EmbeddingError::ResponseError(format!(
+                    "response path `{}` not found in response: {}",
+                    self.response_path.join("."),
+                    response
+                ))
+            })?;
+        }
+
+        current
+            .as_array()
+            .ok_or_else(|| {
+                EmbeddingError::ResponseError(format!(
+                    "response path `{}` did not resolve to an array: {}",
+                    self.response_path.join("."),
+                    current
+                ))
+            })?
+            .iter()
+            .map(|embedding| {
+                embedding
+                    .as_array()
+                    .ok_or_else(|| {
+                        EmbeddingError::ResponseError(format!(
+                            "expected an array of numbers, got: {}",
+                            embedding
+                        ))
+                    })?
+                    .iter()
+                    .map(|n| {
+                        n.as_f64().ok_or_else(|| {
+                            EmbeddingError::ResponseError(format!(
+                                "expected a number, got: {}",
+                                n
+                            ))
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl embeddings::EmbeddingModel for EmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    #[cfg_attr(feature = "worker", worker::send)]
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        let documents_vec = documents.into_iter().collect::<Vec<_>>();
+
+        let chunks = documents_vec
+            .chunks(Self::MAX_DOCUMENTS)
+            .map(|chunk| chunk.to_vec());
+
+        let chunk_results: Vec<Vec<embeddings::Embedding>> = stream::iter(chunks)
+            .map(|chunk| self.embed_chunk(chunk))
+            .buffered(self.request_parallelism.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(chunk_results.into_iter().flatten().collect())
+    }
+}
+
+impl EmbeddingModel {
+    /// Embeds a single chunk of at most `MAX_DOCUMENTS` documents in one request. This is the
+    /// unit of work dispatched concurrently by [`EmbeddingModel::embed_texts`].
+    async fn embed_chunk(
+        &self,
+        documents_vec: Vec<String>,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        let request_body = self.render_request(&documents_vec);
+
+        let mut request = self.http_client.post(&self.url).json(&request_body);
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(EmbeddingError::ProviderError(format!(
+                "Request failed with status {}: {}",
+                status, error_text
+            )));
+        }
+
+        let response_json = response.json::<Value>().await?;
+        let vectors = self.extract_embeddings(response_json)?;
+
+        if vectors.len() != documents_vec.len() {
+            return Err(EmbeddingError::ResponseError(
+                "Response data length does not match input length".into(),
+            ));
+        }
+
+        Ok(vectors
+            .into_iter()
+            .zip(documents_vec.into_iter())
+            .map(|(vec, document)| embeddings::Embedding { document, vec })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn model(request_template: Value, response_path: Vec<&str>) -> EmbeddingModel {
+        EmbeddingModel::new(
+            "https://example.com/embed",
+            request_template,
+            response_path.into_iter().map(String::from).collect(),
+            3,
+        )
+    }
+
+    #[test]
+    fn render_request_substitutes_nested_placeholder() {
+        let model = model(
+            json!({"model": "rest-model", "input": INPUT_PLACEHOLDER}),
+            vec!["embeddings"],
+        );
+
+        let rendered = model.render_request(&["a".into(), "b".into()]);
+
+        assert_eq!(
+            rendered,
+            json!({"model": "rest-model", "input": ["a", "b"]})
+        );
+    }
+
+    #[test]
+    fn render_request_leaves_other_values_untouched() {
+        let model = model(
+            json!({"model": "rest-model", "input": INPUT_PLACEHOLDER, "stream": false}),
+            vec!["embeddings"],
+        );
+
+        let rendered = model.render_request(&["a".into()]);
+
+        assert_eq!(rendered["model"], json!("rest-model"));
+        assert_eq!(rendered["stream"], json!(false));
+    }
+
+    #[test]
+    fn extract_embeddings_walks_nested_path() {
+        let model = model(json!({"input": INPUT_PLACEHOLDER}), vec!["data", "embeddings"]);
+
+        let response = json!({"data": {"embeddings": [[1.0, 2.0], [3.0, 4.0]]}});
+
+        let embeddings = model.extract_embeddings(response).unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+    }
+
+    #[test]
+    fn extract_embeddings_errors_on_missing_path() {
+        let model = model(json!({"input": INPUT_PLACEHOLDER}), vec!["missing"]);
+
+        let result = model.extract_embeddings(json!({"data": []}));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn extract_embeddings_errors_on_non_array_path() {
+        let model = model(json!({"input": INPUT_PLACEHOLDER}), vec!["embeddings"]);
+
+        let result = model.extract_embeddings(json!({"embeddings": "not an array"}));
+
+        assert!(result.is_err());
+    }
+}
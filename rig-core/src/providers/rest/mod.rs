@@ -0,0 +1,10 @@
+//! Generic REST provider.
+//!
+//! Unlike the vendor-specific providers (such as OpenAI), this module targets any HTTP
+//! embedding endpoint by letting the caller describe the request/response shape themselves. It
+//! exists so that self-hosted or otherwise non-standard embedding APIs don't each need a
+//! hand-written provider module.
+
+pub mod embedding;
+
+pub use embedding::EmbeddingModel;